@@ -1,74 +1,90 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod model;
+mod persistence;
+mod staged_transaction;
+
 use eframe::egui;
 
+use model::{InventoryItem, ItemFlag, Transaction, TransactionType};
+use persistence::{BatchLine, Command, PersistenceHandle};
+use staged_transaction::{CommittedBatch, ItemStateProxy, TransactionBuilder, UndoStack};
+
 #[derive(Clone, PartialEq, Eq)]
 enum Tab {
     Inventory,
     AddItem,
     Warehousing,
     Shipping,
+    Transfer,
+    Assemble,
 }
 
-#[derive(Clone)]
-struct InventoryItem {
-    id: u32,
-    name: String,
-    sku: String,
-    unit: String,
-    location: String,
-    quantity_on_hand: i32,
+/// Sortable columns of the inventory listing (one row per item/location).
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Name,
+    Sku,
+    Location,
+    OnHand,
 }
 
-#[derive(Clone)]
-enum TransactionType {
-    Warehousing,
-    Shipping,
-}
-
-#[derive(Clone)]
-struct Transaction {
-    date: (i32, u32, u32), // (year, month, day)
-    item_id: u32,
-    quantity: i32,
-    note: String,
-    txn_type: TransactionType,
-}
+const INVENTORY_PAGE_SIZE: usize = 20;
 
+#[derive(Default)]
 struct AddItemForm {
     name: String,
     sku: String,
     unit: String,
     location: String,
     quantity_text: String,
+    reorder_point_text: String,
     status: String,
 }
 
-impl Default for AddItemForm {
-    fn default() -> Self {
-        Self {
-            name: String::new(),
-            sku: String::new(),
-            unit: String::new(),
-            location: String::new(),
-            quantity_text: String::new(),
-            status: String::new(),
-        }
-    }
+#[derive(Default)]
+struct MovementForm {
+    item_index: usize,
+    location: String,
+    quantity_text: String,
+    note: String,
+    status: String,
 }
 
-struct MovementForm {
+#[derive(Default)]
+struct TransferForm {
     item_index: usize,
+    from_location: String,
+    to_location: String,
     quantity_text: String,
     note: String,
     status: String,
 }
 
-impl Default for MovementForm {
+/// One row of a not-yet-saved recipe being edited in the Assemble tab.
+struct RecipeComponentRow {
+    item_index: usize,
+    quantity_text: String,
+}
+
+struct AssembleForm {
+    item_index: usize,
+    output_quantity_text: String,
+    component_rows: Vec<RecipeComponentRow>,
+    location: String,
+    batches_text: String,
+    note: String,
+    status: String,
+}
+
+impl Default for AssembleForm {
     fn default() -> Self {
         Self {
             item_index: 0,
-            quantity_text: String::new(),
+            output_quantity_text: "1".to_string(),
+            component_rows: Vec::new(),
+            location: String::new(),
+            batches_text: String::new(),
             note: String::new(),
             status: String::new(),
         }
@@ -76,9 +92,16 @@ impl Default for MovementForm {
 }
 
 struct MyApp {
+    // Cached view of the persisted state; refreshed each frame from
+    // `persistence.snapshot` so reading state never blocks on disk I/O.
+    // Posting a batch (`post_batch`) does block until the worker has
+    // committed or rejected it.
     items: Vec<InventoryItem>,
     transactions: Vec<Transaction>,
-    next_item_id: u32,
+    persistence: PersistenceHandle,
+    // Keeps the background worker's tokio runtime alive for the app's lifetime.
+    _runtime: tokio::runtime::Runtime,
+
     selected_tab: Tab,
 
     // Date selection (calendar)
@@ -90,18 +113,34 @@ struct MyApp {
     add_item_form: AddItemForm,
     warehousing_form: MovementForm,
     shipping_form: MovementForm,
+    transfer_form: TransferForm,
+    assemble_form: AssembleForm,
+
+    // Staged (not-yet-committed) multi-line transactions, one builder per tab.
+    warehousing_builder: TransactionBuilder,
+    shipping_builder: TransactionBuilder,
+    undo_stack: UndoStack,
 
     // UI helpers
     inventory_filter: String,
+    inventory_flagged_only: bool,
+    inventory_group_by_location: bool,
+    inventory_sort: SortColumn,
+    inventory_sort_desc: bool,
+    inventory_page: usize,
 }
 
-impl Default for MyApp {
-    fn default() -> Self {
+impl MyApp {
+    fn new() -> Self {
         let (year, month, day) = current_local_ymd();
+        let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+        let persistence = persistence::spawn(std::path::Path::new("inventory.sqlite3"), &runtime);
+        let initial = persistence.snapshot.borrow().clone();
         Self {
-            items: Vec::new(),
-            transactions: Vec::new(),
-            next_item_id: 1,
+            items: initial.items,
+            transactions: initial.transactions,
+            persistence,
+            _runtime: runtime,
             selected_tab: Tab::Inventory,
             selected_year: year,
             selected_month: month,
@@ -109,13 +148,129 @@ impl Default for MyApp {
             add_item_form: AddItemForm::default(),
             warehousing_form: MovementForm::default(),
             shipping_form: MovementForm::default(),
+            transfer_form: TransferForm::default(),
+            assemble_form: AssembleForm::default(),
+            warehousing_builder: TransactionBuilder::default(),
+            shipping_builder: TransactionBuilder::default(),
+            undo_stack: UndoStack::default(),
             inventory_filter: String::new(),
+            inventory_flagged_only: false,
+            inventory_group_by_location: false,
+            inventory_sort: SortColumn::Name,
+            inventory_sort_desc: false,
+            inventory_page: 0,
+        }
+    }
+
+    /// Posts a staged batch: sends it to the persistence worker, then (if the
+    /// worker's own stock check accepted it) records the before/after
+    /// quantity of every touched item so the whole batch can be undone as a
+    /// unit. The client-side `TransactionBuilder::validate` catches the
+    /// common case early, but the worker re-checks against the database
+    /// inside the same SQLite transaction as the write - the authoritative
+    /// check - since the UI's snapshot can be stale relative to batches
+    /// other tabs just posted.
+    fn post_batch(
+        &mut self,
+        builder_lines: &[staged_transaction::ProposedLine],
+        txn_type: TransactionType,
+    ) -> Result<(), String> {
+        let date = (self.selected_year, self.selected_month, self.selected_day);
+
+        let mut entries: Vec<ItemStateProxy> = Vec::new();
+        let mut batch_lines: Vec<BatchLine> = Vec::new();
+        for line in builder_lines {
+            let Some(item) = self.items.get(line.item_index) else {
+                continue;
+            };
+            match entries
+                .iter_mut()
+                .find(|e| e.item_id == item.id && e.location == line.location)
+            {
+                Some(entry) => entry.after += line.delta,
+                None => {
+                    let before = item.quantity_at(&line.location);
+                    entries.push(ItemStateProxy {
+                        item_id: item.id,
+                        location: line.location.clone(),
+                        before,
+                        after: before + line.delta,
+                    })
+                }
+            }
+            batch_lines.push(BatchLine {
+                item_id: item.id,
+                location: line.location.clone(),
+                delta: line.delta,
+                note: line.note.clone(),
+                txn_type: txn_type.clone(),
+                date,
+            });
+        }
+
+        let (responder, response) = tokio::sync::oneshot::channel();
+        let _ = self
+            .persistence
+            .commands
+            .blocking_send(Command::ApplyBatch(batch_lines, responder));
+        let result = self
+            ._runtime
+            .block_on(response)
+            .unwrap_or_else(|_| Err("persistence worker shut down".to_string()));
+        if result.is_ok() {
+            self.undo_stack.push(CommittedBatch {
+                txn_type,
+                entries,
+            });
+        }
+        result
+    }
+
+    /// Sends the `(item_id, location, delta)` triples from an undo/redo step
+    /// to the persistence worker as a new compensating batch, so the
+    /// transaction log keeps a complete history instead of erasing it. Logged
+    /// under the original batch's `txn_type` rather than guessed from the
+    /// sign of the delta, so undoing a Transfer or Assembly still shows up as
+    /// one in the audit trail instead of an ordinary receive/ship.
+    fn apply_reversal(
+        &self,
+        date: (i32, u32, u32),
+        txn_type: TransactionType,
+        reversal: Vec<(u32, String, i32)>,
+    ) {
+        let lines = reversal
+            .into_iter()
+            .map(|(item_id, location, delta)| BatchLine {
+                item_id,
+                location,
+                delta,
+                note: "undo/redo".to_string(),
+                txn_type: txn_type.clone(),
+                date,
+            })
+            .collect();
+        let (responder, _response) = tokio::sync::oneshot::channel();
+        let _ = self
+            .persistence
+            .commands
+            .blocking_send(Command::ApplyBatch(lines, responder));
+    }
+
+    /// Pulls the latest snapshot published by the persistence worker, if any
+    /// writes have landed since the last frame.
+    fn refresh_from_snapshot(&mut self) {
+        if self.persistence.snapshot.has_changed().unwrap_or(false) {
+            let snapshot = self.persistence.snapshot.borrow_and_update().clone();
+            self.items = snapshot.items;
+            self.transactions = snapshot.transactions;
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.refresh_from_snapshot();
+
         egui::TopBottomPanel::top("top_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.label("Date:");
@@ -125,6 +280,8 @@ impl eframe::App for MyApp {
                 ui.selectable_value(&mut self.selected_tab, Tab::AddItem, "Add Item");
                 ui.selectable_value(&mut self.selected_tab, Tab::Warehousing, "Warehousing");
                 ui.selectable_value(&mut self.selected_tab, Tab::Shipping, "Shipping");
+                ui.selectable_value(&mut self.selected_tab, Tab::Transfer, "Transfer");
+                ui.selectable_value(&mut self.selected_tab, Tab::Assemble, "Assemble");
             });
         });
 
@@ -133,6 +290,8 @@ impl eframe::App for MyApp {
             Tab::AddItem => self.add_item_tab_ui(ui),
             Tab::Warehousing => self.movement_tab_ui(ui, true),
             Tab::Shipping => self.movement_tab_ui(ui, false),
+            Tab::Transfer => self.transfer_tab_ui(ui),
+            Tab::Assemble => self.assemble_tab_ui(ui),
         });
 
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
@@ -145,34 +304,124 @@ impl eframe::App for MyApp {
                 }
             });
         });
+
+        // The worker may publish a fresh snapshot between frames (e.g. once a
+        // write we just enqueued lands); keep polling for it.
+        ctx.request_repaint_after(std::time::Duration::from_millis(200));
     }
 }
 
 fn current_local_ymd() -> (i32, u32, u32) {
-    // Minimal stand-in for a date; UI lets user change it anyway
-    (2025, 1, 1)
+    use chrono::Datelike;
+    let today = chrono::Local::now().date_naive();
+    (today.year(), today.month(), today.day())
+}
+
+/// Number of days in `month` of `year` (leap years included), via the
+/// distance to the first day of the following month.
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next_month_first = if month == 12 {
+        chrono::NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        chrono::NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .expect("valid next-month boundary");
+    let this_month_first =
+        chrono::NaiveDate::from_ymd_opt(year, month, 1).expect("valid month start");
+    (next_month_first - this_month_first).num_days() as u32
+}
+
+/// Humanized relative age (e.g. "2 hours ago") of the moment a transaction
+/// was actually posted. Based on `posted_at` (a real timestamp stamped by
+/// the persistence worker) rather than the transaction's logical `date`,
+/// which is only day-resolution and can be backdated by the user - neither
+/// of which can support hour-level granularity.
+fn humanize_relative_age(posted_at: chrono::NaiveDateTime) -> String {
+    let age = chrono::Local::now().naive_local() - posted_at;
+    match age.num_seconds() {
+        s if s < 0 => "in the future".to_string(),
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{} minutes ago", s / 60),
+        s if s < 86_400 => format!("{} hours ago", s / 3600),
+        s if s < 2 * 86_400 => "yesterday".to_string(),
+        s if s < 7 * 86_400 => format!("{} days ago", s / 86_400),
+        s if s < 30 * 86_400 => format!("{} weeks ago", s / (7 * 86_400)),
+        s if s < 365 * 86_400 => format!("{} months ago", s / (30 * 86_400)),
+        s => format!("{} years ago", s / (365 * 86_400)),
+    }
+}
+
+/// Renders one clickable, sortable column header for the inventory grid.
+/// Takes the sort state as separate field reborrows rather than `&mut
+/// MyApp` - it's called from inside the `egui::Grid::show` closure in
+/// `inventory_tab_ui` alongside rows borrowed from `self.items`, and a
+/// `&mut self` method there would conflict with that borrow.
+fn sort_header(
+    ui: &mut egui::Ui,
+    label: &str,
+    column: SortColumn,
+    sort: &mut SortColumn,
+    sort_desc: &mut bool,
+    page: &mut usize,
+) {
+    let arrow = if *sort == column {
+        if *sort_desc {
+            " v"
+        } else {
+            " ^"
+        }
+    } else {
+        ""
+    };
+    if ui.button(format!("{}{}", label, arrow)).clicked() {
+        if *sort == column {
+            *sort_desc = !*sort_desc;
+        } else {
+            *sort = column;
+            *sort_desc = false;
+        }
+        *page = 0;
+    }
 }
 
 impl MyApp {
     fn date_picker_ui(&mut self, ui: &mut egui::Ui) {
+        let mut year_changed = false;
+        let mut month_changed = false;
         egui::ComboBox::from_label("")
             .selected_text(format!("{}", self.selected_year))
             .show_ui(ui, |ui| {
                 for year in (2000..=2035).rev() {
-                    ui.selectable_value(&mut self.selected_year, year, year.to_string());
+                    if ui
+                        .selectable_value(&mut self.selected_year, year, year.to_string())
+                        .changed()
+                    {
+                        year_changed = true;
+                    }
                 }
             });
         egui::ComboBox::from_label("")
             .selected_text(format!("{:02}", self.selected_month))
             .show_ui(ui, |ui| {
                 for m in 1..=12 {
-                    ui.selectable_value(&mut self.selected_month, m, format!("{:02}", m));
+                    if ui
+                        .selectable_value(&mut self.selected_month, m, format!("{:02}", m))
+                        .changed()
+                    {
+                        month_changed = true;
+                    }
                 }
             });
+
+        let max_day = days_in_month(self.selected_year, self.selected_month);
+        if (year_changed || month_changed) && self.selected_day > max_day {
+            self.selected_day = max_day;
+        }
+
         egui::ComboBox::from_label("")
             .selected_text(format!("{:02}", self.selected_day))
             .show_ui(ui, |ui| {
-                for d in 1..=31 {
+                for d in 1..=max_day {
                     ui.selectable_value(&mut self.selected_day, d, format!("{:02}", d));
                 }
             });
@@ -181,46 +430,208 @@ impl MyApp {
     fn inventory_tab_ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("Filter");
-            ui.text_edit_singleline(&mut self.inventory_filter);
+            if ui
+                .text_edit_singleline(&mut self.inventory_filter)
+                .changed()
+            {
+                self.inventory_page = 0;
+            }
+            if ui
+                .checkbox(&mut self.inventory_flagged_only, "Flagged only")
+                .changed()
+            {
+                self.inventory_page = 0;
+            }
+            if ui
+                .checkbox(&mut self.inventory_group_by_location, "Group by location")
+                .changed()
+            {
+                self.inventory_page = 0;
+            }
         });
         ui.separator();
 
         let filter_lc = self.inventory_filter.to_lowercase();
-        let mut rows: Vec<_> = self
+        let items: Vec<_> = self
             .items
             .iter()
             .filter(|item| {
+                if self.inventory_flagged_only && item.flags().is_empty() {
+                    return false;
+                }
                 if filter_lc.is_empty() {
                     true
                 } else {
                     item.name.to_lowercase().contains(&filter_lc)
                         || item.sku.to_lowercase().contains(&filter_lc)
-                        || item.location.to_lowercase().contains(&filter_lc)
+                        || item
+                            .stock
+                            .iter()
+                            .any(|s| s.location.to_lowercase().contains(&filter_lc))
                 }
             })
-            .cloned()
             .collect();
-        rows.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // One row per (item, location); an item with no stock entries
+        // anywhere simply contributes no rows.
+        struct Row<'a> {
+            item: &'a InventoryItem,
+            location: &'a str,
+            quantity_on_hand: i32,
+        }
+        let mut rows: Vec<Row> = items
+            .iter()
+            .flat_map(|item| {
+                item.stock.iter().map(move |s| Row {
+                    item,
+                    location: &s.location,
+                    quantity_on_hand: s.quantity_on_hand,
+                })
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = match self.inventory_sort {
+                SortColumn::Name => a.item.name.cmp(&b.item.name),
+                SortColumn::Sku => a.item.sku.cmp(&b.item.sku),
+                SortColumn::Location => a.location.cmp(b.location),
+                SortColumn::OnHand => a.quantity_on_hand.cmp(&b.quantity_on_hand),
+            };
+            let ordering = if self.inventory_sort_desc {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            // Grouping clusters rows under a "-- location --" header, so the
+            // location always wins the sort; the chosen column only orders
+            // rows within a group.
+            if self.inventory_group_by_location {
+                a.location.cmp(b.location).then(ordering)
+            } else {
+                ordering
+            }
+        });
+
+        let total_rows = rows.len();
+        let page_count = total_rows.div_ceil(INVENTORY_PAGE_SIZE).max(1);
+        if self.inventory_page >= page_count {
+            self.inventory_page = page_count - 1;
+        }
+        let page_start = self.inventory_page * INVENTORY_PAGE_SIZE;
+        let page_rows = &rows[page_start..(page_start + INVENTORY_PAGE_SIZE).min(total_rows)];
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(self.inventory_page > 0, |ui| {
+                if ui.button("< Prev").clicked() {
+                    self.inventory_page -= 1;
+                }
+            });
+            ui.label(format!("Page {} / {}", self.inventory_page + 1, page_count));
+            ui.add_enabled_ui(self.inventory_page + 1 < page_count, |ui| {
+                if ui.button("Next >").clicked() {
+                    self.inventory_page += 1;
+                }
+            });
+        });
+        ui.separator();
 
         egui::ScrollArea::both().show(ui, |ui| {
             egui::Grid::new("inventory_grid")
                 .striped(true)
                 .show(ui, |ui| {
-                    ui.strong("Name");
-                    ui.strong("SKU");
+                    ui.label(""); // row-number gutter
+                    sort_header(
+                        ui,
+                        "Name",
+                        SortColumn::Name,
+                        &mut self.inventory_sort,
+                        &mut self.inventory_sort_desc,
+                        &mut self.inventory_page,
+                    );
+                    sort_header(
+                        ui,
+                        "SKU",
+                        SortColumn::Sku,
+                        &mut self.inventory_sort,
+                        &mut self.inventory_sort_desc,
+                        &mut self.inventory_page,
+                    );
                     ui.strong("Unit");
-                    ui.strong("Location");
-                    ui.strong("On hand");
+                    sort_header(
+                        ui,
+                        "Location",
+                        SortColumn::Location,
+                        &mut self.inventory_sort,
+                        &mut self.inventory_sort_desc,
+                        &mut self.inventory_page,
+                    );
+                    sort_header(
+                        ui,
+                        "On hand",
+                        SortColumn::OnHand,
+                        &mut self.inventory_sort,
+                        &mut self.inventory_sort_desc,
+                        &mut self.inventory_page,
+                    );
+                    ui.strong("Total on hand");
+                    ui.strong("Flags");
+                    ui.strong("");
                     ui.end_row();
 
-                    for item in rows.iter() {
-                        ui.label(&item.name);
-                        ui.label(&item.sku);
-                        ui.label(&item.unit);
-                        ui.label(&item.location);
-                        ui.monospace(item.quantity_on_hand.to_string());
+                    let mut toggle_discontinued: Option<(u32, bool)> = None;
+                    let mut last_location: Option<&str> = None;
+                    for (offset, row) in page_rows.iter().enumerate() {
+                        if self.inventory_group_by_location && last_location != Some(row.location)
+                        {
+                            ui.strong(format!("-- {} --", row.location));
+                            ui.end_row();
+                            last_location = Some(row.location);
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.monospace((page_start + offset + 1).to_string());
+                        });
+                        ui.label(&row.item.name);
+                        ui.label(&row.item.sku);
+                        ui.label(&row.item.unit);
+                        ui.label(row.location);
+                        ui.monospace(row.quantity_on_hand.to_string());
+                        ui.monospace(row.item.total_on_hand().to_string());
+
+                        let flags = row.item.flags();
+                        let flags_text = flags
+                            .iter()
+                            .map(|f| f.label())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let color = if flags.contains(&ItemFlag::OutOfStock) {
+                            egui::Color32::RED
+                        } else if flags.contains(&ItemFlag::LowStock) {
+                            egui::Color32::from_rgb(200, 150, 0)
+                        } else if flags.contains(&ItemFlag::Discontinued) {
+                            egui::Color32::GRAY
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        ui.colored_label(color, flags_text);
+
+                        let button_label = if row.item.discontinued {
+                            "Reinstate"
+                        } else {
+                            "Discontinue"
+                        };
+                        if ui.small_button(button_label).clicked() {
+                            toggle_discontinued = Some((row.item.id, !row.item.discontinued));
+                        }
                         ui.end_row();
                     }
+                    if let Some((item_id, discontinued)) = toggle_discontinued {
+                        let _ = self
+                            .persistence
+                            .commands
+                            .blocking_send(Command::SetDiscontinued {
+                                item_id,
+                                discontinued,
+                            });
+                    }
                 });
         });
     }
@@ -249,44 +660,243 @@ impl MyApp {
                 ui.label("Initial Qty");
                 ui.text_edit_singleline(&mut form.quantity_text);
                 ui.end_row();
+
+                ui.label("Reorder point");
+                ui.text_edit_singleline(&mut form.reorder_point_text);
+                ui.end_row();
             });
 
         if ui.button("Add Item").clicked() {
             let qty: i32 = form.quantity_text.trim().parse().unwrap_or(0);
+            let reorder_point: i32 = form.reorder_point_text.trim().parse().unwrap_or(0);
             if form.name.trim().is_empty() || form.sku.trim().is_empty() {
                 form.status = "Name and SKU are required".to_string();
             } else {
-                let item = InventoryItem {
-                    id: self.next_item_id,
+                let _ = self.persistence.commands.blocking_send(Command::AddItem {
                     name: form.name.trim().to_string(),
                     sku: form.sku.trim().to_string(),
                     unit: form.unit.trim().to_string(),
                     location: form.location.trim().to_string(),
                     quantity_on_hand: qty,
-                };
-                self.next_item_id += 1;
-                self.items.push(item);
+                    reorder_point,
+                });
                 form.status = "Item added".to_string();
                 form.name.clear();
                 form.sku.clear();
                 form.unit.clear();
                 form.location.clear();
                 form.quantity_text.clear();
+                form.reorder_point_text.clear();
             }
         }
     }
 
+    /// Undo/Redo buttons for the single, chronological undo stack shared by
+    /// every tab that posts batches (Warehousing, Shipping, Transfer,
+    /// Assemble). They always act on the single most recent batch regardless
+    /// of which tab posted it, so every one of those tabs renders them rather
+    /// than just one - otherwise a user on, say, Warehousing could silently
+    /// undo a Transfer posted from another tab with no indication that's
+    /// what just happened.
+    fn undo_redo_controls_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.undo_stack.can_undo(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                let date = (self.selected_year, self.selected_month, self.selected_day);
+                if let Some((txn_type, reversal)) = self.undo_stack.undo() {
+                    self.apply_reversal(date, txn_type, reversal);
+                }
+            }
+            if ui
+                .add_enabled(self.undo_stack.can_redo(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                let date = (self.selected_year, self.selected_month, self.selected_day);
+                if let Some((txn_type, redo)) = self.undo_stack.redo() {
+                    self.apply_reversal(date, txn_type, redo);
+                }
+            }
+        });
+        ui.separator();
+    }
+
     fn movement_tab_ui(&mut self, ui: &mut egui::Ui, is_warehousing: bool) {
+        if self.items.is_empty() {
+            ui.label("No items available. Add an item first.");
+            return;
+        }
+
+        self.undo_redo_controls_ui(ui);
+
         let form = if is_warehousing {
             &mut self.warehousing_form
         } else {
             &mut self.shipping_form
         };
+        if form.item_index >= self.items.len() {
+            form.item_index = 0;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Item");
+            egui::ComboBox::from_label("")
+                .selected_text(
+                    self.items
+                        .get(form.item_index)
+                        .map(|i| i.name.clone())
+                        .unwrap_or_else(|| "<no items>".into()),
+                )
+                .show_ui(ui, |ui| {
+                    for (idx, item) in self.items.iter().enumerate() {
+                        ui.selectable_value(&mut form.item_index, idx, item.name.clone());
+                    }
+                });
+        });
+
+        egui::Grid::new("movement_form")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Location");
+                ui.text_edit_singleline(&mut form.location);
+                ui.end_row();
 
+                ui.label("Quantity");
+                ui.text_edit_singleline(&mut form.quantity_text);
+                ui.end_row();
+
+                ui.label("Note");
+                ui.text_edit_singleline(&mut form.note);
+                ui.end_row();
+            });
+
+        let builder = if is_warehousing {
+            &mut self.warehousing_builder
+        } else {
+            &mut self.shipping_builder
+        };
+
+        if ui.button("Add Line").clicked() {
+            let qty: i32 = form.quantity_text.trim().parse().unwrap_or(0);
+            let sign = if is_warehousing { 1 } else { -1 };
+            builder.add_line(
+                form.item_index,
+                form.location.trim().to_string(),
+                sign * qty,
+                form.note.clone(),
+            );
+            form.quantity_text.clear();
+            form.note.clear();
+        }
+
+        ui.separator();
+        ui.strong("Staged lines");
+        let mut remove_index = None;
+        for (idx, line) in builder.lines().iter().enumerate() {
+            let item_name = self
+                .items
+                .get(line.item_index)
+                .map(|i| i.name.clone())
+                .unwrap_or_else(|| "?".into());
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} @ {} x{} - {}",
+                    item_name, line.location, line.delta, line.note
+                ));
+                if ui.small_button("Remove").clicked() {
+                    remove_index = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_index {
+            builder.remove_line(idx);
+        }
+
+        let button_label = if is_warehousing {
+            "Post Receiving Batch"
+        } else {
+            "Post Shipping Batch"
+        };
+        if ui
+            .add_enabled(!builder.is_empty(), egui::Button::new(button_label))
+            .clicked()
+        {
+            match builder.validate(&self.items) {
+                Ok(()) => {
+                    let lines = builder.lines().to_vec();
+                    builder.clear();
+                    let txn_type = if is_warehousing {
+                        TransactionType::Warehousing
+                    } else {
+                        TransactionType::Shipping
+                    };
+                    // post_batch takes &mut self, so it can't run while `form`
+                    // (a live reborrow of self.warehousing_form /
+                    // self.shipping_form) is still in scope - resolve it into
+                    // a local first, then re-borrow the form to apply it.
+                    let result = self.post_batch(&lines, txn_type);
+                    let form = if is_warehousing {
+                        &mut self.warehousing_form
+                    } else {
+                        &mut self.shipping_form
+                    };
+                    form.status = match result {
+                        Ok(()) => "Batch posted".into(),
+                        Err(reason) => reason,
+                    };
+                }
+                Err(reason) => form.status = reason,
+            }
+        }
+
+        ui.separator();
+        ui.strong("Recent transactions");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for txn in self.transactions.iter().rev().take(20) {
+                ui.label(self.format_transaction(txn));
+            }
+        });
+    }
+
+    fn format_transaction(&self, txn: &Transaction) -> String {
+        let kind = match txn.txn_type {
+            TransactionType::Warehousing => "IN",
+            TransactionType::Shipping => "OUT",
+            TransactionType::Transfer => "XFER",
+            TransactionType::Assembly => "ASM",
+        };
+        let item_name = self
+            .items
+            .iter()
+            .find(|i| i.id == txn.item_id)
+            .map(|i| i.name.clone())
+            .unwrap_or_else(|| "?".into());
+        format!(
+            "{}-{:02}-{:02} ({}) [{}] {} @ {} x{} - {}",
+            txn.date.0,
+            txn.date.1,
+            txn.date.2,
+            humanize_relative_age(txn.posted_at),
+            kind,
+            item_name,
+            txn.location,
+            txn.quantity,
+            txn.note
+        )
+    }
+}
+
+impl MyApp {
+    fn transfer_tab_ui(&mut self, ui: &mut egui::Ui) {
         if self.items.is_empty() {
             ui.label("No items available. Add an item first.");
             return;
         }
+
+        self.undo_redo_controls_ui(ui);
+
+        let form = &mut self.transfer_form;
         if form.item_index >= self.items.len() {
             form.item_index = 0;
         }
@@ -307,9 +917,17 @@ impl MyApp {
                 });
         });
 
-        egui::Grid::new("movement_form")
+        egui::Grid::new("transfer_form")
             .num_columns(2)
             .show(ui, |ui| {
+                ui.label("From location");
+                ui.text_edit_singleline(&mut form.from_location);
+                ui.end_row();
+
+                ui.label("To location");
+                ui.text_edit_singleline(&mut form.to_location);
+                ui.end_row();
+
                 ui.label("Quantity");
                 ui.text_edit_singleline(&mut form.quantity_text);
                 ui.end_row();
@@ -319,29 +937,42 @@ impl MyApp {
                 ui.end_row();
             });
 
-        let button_label = if is_warehousing { "Receive" } else { "Ship" };
-        if ui.button(button_label).clicked() {
+        if ui.button("Transfer").clicked() {
             let qty: i32 = form.quantity_text.trim().parse().unwrap_or(0);
-            let sign = if is_warehousing { 1 } else { -1 };
-            let adj = sign * qty;
-            if let Some(item) = self.items.get_mut(form.item_index) {
-                let new_qoh = item.quantity_on_hand + adj;
-                if new_qoh < 0 {
-                    form.status = "Insufficient stock".into();
+            let from = form.from_location.trim().to_string();
+            let to = form.to_location.trim().to_string();
+            if from.is_empty() || to.is_empty() || from == to {
+                form.status = "Pick two distinct locations".into();
+            } else if qty <= 0 {
+                form.status = "Quantity must be positive".into();
+            } else if let Some(item) = self.items.get(form.item_index) {
+                if item.quantity_at(&from) < qty {
+                    form.status = "Insufficient stock at source location".into();
                 } else {
-                    item.quantity_on_hand = new_qoh;
-                    self.transactions.push(Transaction {
-                        date: (self.selected_year, self.selected_month, self.selected_day),
-                        item_id: item.id,
-                        quantity: qty,
-                        note: form.note.clone(),
-                        txn_type: if is_warehousing {
-                            TransactionType::Warehousing
-                        } else {
-                            TransactionType::Shipping
+                    let lines = vec![
+                        staged_transaction::ProposedLine {
+                            item_index: form.item_index,
+                            location: from,
+                            delta: -qty,
+                            note: form.note.clone(),
                         },
-                    });
-                    form.status = "Recorded".into();
+                        staged_transaction::ProposedLine {
+                            item_index: form.item_index,
+                            location: to,
+                            delta: qty,
+                            note: form.note.clone(),
+                        },
+                    ];
+                    // post_batch takes &mut self, so it can't run while
+                    // `form` (a live reborrow of self.transfer_form) is still
+                    // in scope - resolve it into a local first, then
+                    // re-borrow the form to apply it.
+                    let result = self.post_batch(&lines, TransactionType::Transfer);
+                    let form = &mut self.transfer_form;
+                    form.status = match result {
+                        Ok(()) => "Transfer posted".into(),
+                        Err(reason) => reason,
+                    };
                     form.quantity_text.clear();
                     form.note.clear();
                 }
@@ -349,23 +980,235 @@ impl MyApp {
         }
 
         ui.separator();
-        ui.strong("Recent transactions");
+        ui.strong("Recent transfers");
         egui::ScrollArea::vertical().show(ui, |ui| {
-            for txn in self.transactions.iter().rev().take(20) {
-                let kind = match txn.txn_type {
-                    TransactionType::Warehousing => "IN",
-                    TransactionType::Shipping => "OUT",
-                };
-                let item_name = self
-                    .items
-                    .iter()
-                    .find(|i| i.id == txn.item_id)
-                    .map(|i| i.name.clone())
-                    .unwrap_or_else(|| "?".into());
-                ui.label(format!(
-                    "{}-{:02}-{:02} [{}] {} x{} - {}",
-                    txn.date.0, txn.date.1, txn.date.2, kind, item_name, txn.quantity, txn.note
-                ));
+            for txn in self
+                .transactions
+                .iter()
+                .rev()
+                .filter(|t| t.txn_type == TransactionType::Transfer)
+                .take(20)
+            {
+                ui.label(self.format_transaction(txn));
+            }
+        });
+    }
+}
+
+impl MyApp {
+    fn assemble_tab_ui(&mut self, ui: &mut egui::Ui) {
+        if self.items.is_empty() {
+            ui.label("No items available. Add an item first.");
+            return;
+        }
+
+        self.undo_redo_controls_ui(ui);
+
+        let form = &mut self.assemble_form;
+        if form.item_index >= self.items.len() {
+            form.item_index = 0;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Item");
+            egui::ComboBox::from_label("")
+                .selected_text(
+                    self.items
+                        .get(form.item_index)
+                        .map(|i| i.name.clone())
+                        .unwrap_or_else(|| "<no items>".into()),
+                )
+                .show_ui(ui, |ui| {
+                    for (idx, item) in self.items.iter().enumerate() {
+                        ui.selectable_value(&mut form.item_index, idx, item.name.clone());
+                    }
+                });
+        });
+
+        ui.separator();
+        ui.strong("Define recipe");
+        ui.horizontal(|ui| {
+            ui.label("Output quantity per batch");
+            ui.text_edit_singleline(&mut form.output_quantity_text);
+        });
+
+        let mut remove_component = None;
+        for (idx, row) in form.component_rows.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source(("recipe_component", idx))
+                    .selected_text(
+                        self.items
+                            .get(row.item_index)
+                            .map(|i| i.name.clone())
+                            .unwrap_or_else(|| "<no items>".into()),
+                    )
+                    .show_ui(ui, |ui| {
+                        for (i, item) in self.items.iter().enumerate() {
+                            ui.selectable_value(&mut row.item_index, i, item.name.clone());
+                        }
+                    });
+                ui.label("quantity per batch");
+                ui.text_edit_singleline(&mut row.quantity_text);
+                if ui.small_button("Remove").clicked() {
+                    remove_component = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_component {
+            form.component_rows.remove(idx);
+        }
+        if ui.button("Add Component").clicked() {
+            form.component_rows.push(RecipeComponentRow {
+                item_index: 0,
+                quantity_text: String::new(),
+            });
+        }
+
+        if ui.button("Save Recipe").clicked() {
+            let output_quantity: i32 = form.output_quantity_text.trim().parse().unwrap_or(0);
+            let components: Vec<(u32, i32)> = form
+                .component_rows
+                .iter()
+                .filter_map(|row| {
+                    let quantity: i32 = row.quantity_text.trim().parse().unwrap_or(0);
+                    if quantity <= 0 {
+                        return None;
+                    }
+                    self.items
+                        .get(row.item_index)
+                        .map(|item| (item.id, quantity))
+                })
+                .collect();
+            if output_quantity <= 0 || components.is_empty() {
+                self.assemble_form.status =
+                    "Recipe needs a positive output quantity and at least one component".into();
+            } else {
+                let item_id = self.items[self.assemble_form.item_index].id;
+                let _ = self.persistence.commands.blocking_send(Command::SetRecipe {
+                    item_id,
+                    output_quantity,
+                    components,
+                });
+                self.assemble_form.status = "Recipe saved".into();
+            }
+        }
+
+        ui.separator();
+        ui.strong("Assemble");
+
+        let form = &self.assemble_form;
+        let Some(item) = self.items.get(form.item_index) else {
+            return;
+        };
+        let Some(recipe) = item.recipe().cloned() else {
+            ui.label("Selected item has no recipe defined yet.");
+            return;
+        };
+
+        ui.label(format!(
+            "Produces {} {} per batch - cost roll-up {} component units",
+            recipe.output_quantity,
+            item.unit,
+            recipe.cost_rollup()
+        ));
+        for component in &recipe.components {
+            let name = self
+                .items
+                .iter()
+                .find(|i| i.id == component.item_id)
+                .map(|i| i.name.clone())
+                .unwrap_or_else(|| "?".into());
+            ui.label(format!("{} x{} per batch", name, component.quantity));
+        }
+
+        let form = &mut self.assemble_form;
+        ui.horizontal(|ui| {
+            ui.label("Location");
+            ui.text_edit_singleline(&mut form.location);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Batches");
+            ui.text_edit_singleline(&mut form.batches_text);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Note");
+            ui.text_edit_singleline(&mut form.note);
+        });
+
+        if ui.button("Assemble").clicked() {
+            let batches: i32 = form.batches_text.trim().parse().unwrap_or(0);
+            let location = form.location.trim().to_string();
+            let note = form.note.clone();
+            let finished_item_index = form.item_index;
+
+            if batches <= 0 || location.is_empty() {
+                self.assemble_form.status = "Pick a location and a positive batch count".into();
+            } else {
+                let mut lines = Vec::new();
+                let mut shortage = None;
+                for component in &recipe.components {
+                    match self
+                        .items
+                        .iter()
+                        .enumerate()
+                        .find(|(_, i)| i.id == component.item_id)
+                    {
+                        Some((component_index, component_item)) => {
+                            let needed = component.quantity * batches;
+                            if component_item.quantity_at(&location) < needed {
+                                shortage = Some(format!(
+                                    "{} short at {} (need {})",
+                                    component_item.name, location, needed
+                                ));
+                                break;
+                            }
+                            lines.push(staged_transaction::ProposedLine {
+                                item_index: component_index,
+                                location: location.clone(),
+                                delta: -needed,
+                                note: note.clone(),
+                            });
+                        }
+                        None => {
+                            shortage =
+                                Some(format!("Unknown component item {}", component.item_id));
+                            break;
+                        }
+                    }
+                }
+
+                match shortage {
+                    Some(reason) => self.assemble_form.status = reason,
+                    None => {
+                        lines.push(staged_transaction::ProposedLine {
+                            item_index: finished_item_index,
+                            location: location.clone(),
+                            delta: recipe.output_quantity * batches,
+                            note,
+                        });
+                        self.assemble_form.status =
+                            match self.post_batch(&lines, TransactionType::Assembly) {
+                                Ok(()) => "Assembly posted".into(),
+                                Err(reason) => reason,
+                            };
+                        self.assemble_form.batches_text.clear();
+                        self.assemble_form.note.clear();
+                    }
+                }
+            }
+        }
+
+        ui.separator();
+        ui.strong("Recent assemblies");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for txn in self
+                .transactions
+                .iter()
+                .rev()
+                .filter(|t| t.txn_type == TransactionType::Assembly)
+                .take(20)
+            {
+                ui.label(self.format_transaction(txn));
             }
         });
     }
@@ -395,6 +1238,20 @@ impl MyApp {
                     Some(self.shipping_form.status.clone())
                 }
             }
+            Tab::Transfer => {
+                if self.transfer_form.status.is_empty() {
+                    None
+                } else {
+                    Some(self.transfer_form.status.clone())
+                }
+            }
+            Tab::Assemble => {
+                if self.assemble_form.status.is_empty() {
+                    None
+                } else {
+                    Some(self.assemble_form.status.clone())
+                }
+            }
             Tab::Inventory => None,
         }
     }
@@ -410,6 +1267,6 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Inventory Manager",
         options,
-        Box::new(|_cc| Ok(Box::<MyApp>::default())),
+        Box::new(|_cc| Ok(Box::new(MyApp::new()))),
     )
 }
@@ -0,0 +1,127 @@
+//! Core domain types shared between the UI layer and the persistence worker.
+
+/// Quantity on hand for one item at one warehouse location.
+#[derive(Clone)]
+pub struct LocationStock {
+    pub location: String,
+    pub quantity_on_hand: i32,
+}
+
+#[derive(Clone)]
+pub struct InventoryItem {
+    pub id: u32,
+    pub name: String,
+    pub sku: String,
+    pub unit: String,
+    pub stock: Vec<LocationStock>,
+    /// Total on-hand below this triggers the `LowStock` flag.
+    pub reorder_point: i32,
+    pub discontinued: bool,
+    /// Bill of materials for assembling this item out of components, if any.
+    pub recipe: Option<Recipe>,
+}
+
+impl InventoryItem {
+    pub fn quantity_at(&self, location: &str) -> i32 {
+        self.stock
+            .iter()
+            .find(|s| s.location == location)
+            .map(|s| s.quantity_on_hand)
+            .unwrap_or(0)
+    }
+
+    pub fn total_on_hand(&self) -> i32 {
+        self.stock.iter().map(|s| s.quantity_on_hand).sum()
+    }
+
+    pub fn recipe(&self) -> Option<&Recipe> {
+        self.recipe.as_ref()
+    }
+
+    /// Reorder/lifecycle flags computed from this item's current state.
+    pub fn flags(&self) -> Vec<ItemFlag> {
+        let mut flags = Vec::new();
+        if self.discontinued {
+            flags.push(ItemFlag::Discontinued);
+        }
+        let total = self.total_on_hand();
+        if total <= 0 {
+            flags.push(ItemFlag::OutOfStock);
+        } else if total < self.reorder_point {
+            flags.push(ItemFlag::LowStock);
+        }
+        flags
+    }
+}
+
+/// One component consumed per batch of a [`Recipe`].
+#[derive(Clone)]
+pub struct RecipeComponent {
+    pub item_id: u32,
+    pub quantity: i32,
+}
+
+/// A bill of materials: assembling one batch consumes `components` and
+/// produces `output_quantity` of the item the recipe is attached to.
+#[derive(Clone)]
+pub struct Recipe {
+    pub output_quantity: i32,
+    pub components: Vec<RecipeComponent>,
+}
+
+impl Recipe {
+    /// Sum of component quantities needed for one batch - the "cost roll-up"
+    /// shown to the user before assembling.
+    pub fn cost_rollup(&self) -> i32 {
+        self.components.iter().map(|c| c.quantity).sum()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ItemFlag {
+    LowStock,
+    OutOfStock,
+    Discontinued,
+}
+
+impl ItemFlag {
+    pub fn label(self) -> &'static str {
+        match self {
+            ItemFlag::LowStock => "Low stock",
+            ItemFlag::OutOfStock => "Out of stock",
+            ItemFlag::Discontinued => "Discontinued",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum TransactionType {
+    Warehousing,
+    Shipping,
+    Transfer,
+    /// Component consumption or finished-good production from assembling a
+    /// recipe. Every line of one assembly shares a `batch_id`.
+    Assembly,
+}
+
+#[derive(Clone)]
+pub struct Transaction {
+    pub date: (i32, u32, u32), // (year, month, day)
+    pub item_id: u32,
+    pub location: String,
+    /// Signed: positive increases stock at `location`, negative decreases it.
+    pub quantity: i32,
+    pub note: String,
+    pub txn_type: TransactionType,
+    /// Lines committed together as one staged transaction share this id.
+    /// Not yet surfaced in the UI, but kept on the model since it's already
+    /// persisted and is the join key anything grouping by batch will need.
+    #[allow(dead_code)]
+    pub batch_id: Option<i64>,
+    /// Wall-clock moment the worker actually persisted this line, stamped
+    /// server-side rather than taken from `date` - `date` is the logical,
+    /// possibly backdated transaction date the user picked, which doesn't
+    /// have the resolution (or necessarily the recency) to support an
+    /// hour-level "N hours ago" display.
+    pub posted_at: chrono::NaiveDateTime,
+}
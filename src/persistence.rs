@@ -0,0 +1,461 @@
+//! Durable storage for inventory items and transactions.
+//!
+//! The SQLite connection lives on a background `tokio` task (the "worker").
+//! The UI never touches the database directly: it sends mutations over an
+//! `mpsc` channel and reads the latest state from a `watch` channel that the
+//! worker republishes after every write. This keeps `MyApp::update` free of
+//! disk I/O even while the transaction log grows. Posting a batch
+//! (`Command::ApplyBatch`) is the one place the UI thread waits on the
+//! worker: it blocks on the reply, which is sent after the batch is
+//! committed or rejected.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::model::{
+    InventoryItem, LocationStock, Recipe, RecipeComponent, Transaction, TransactionType,
+};
+
+/// A full, point-in-time view of the persisted state, as seen by the UI.
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub items: Vec<InventoryItem>,
+    pub transactions: Vec<Transaction>,
+}
+
+/// One line within a staged, multi-line transaction (see
+/// [`crate::staged_transaction::TransactionBuilder`]). Covers receiving,
+/// shipping, and transfers alike - a transfer is just two lines for the same
+/// item at different locations, posted as one batch.
+pub struct BatchLine {
+    pub item_id: u32,
+    pub location: String,
+    /// Signed: positive increases stock at `location`, negative decreases it.
+    pub delta: i32,
+    pub note: String,
+    pub txn_type: TransactionType,
+    pub date: (i32, u32, u32),
+}
+
+/// Mutations the UI wants applied. The worker validates and persists them,
+/// then republishes a fresh `Snapshot`.
+pub enum Command {
+    AddItem {
+        name: String,
+        sku: String,
+        unit: String,
+        location: String,
+        quantity_on_hand: i32,
+        reorder_point: i32,
+    },
+    /// Applies every line in one SQLite transaction: either all lines land,
+    /// or (on error) none do. The persisted rows share a `batch_id` so they
+    /// can later be identified and reversed as a unit.
+    ///
+    /// Stock is re-checked against the database inside that same
+    /// transaction (not just against the caller's possibly-stale snapshot),
+    /// so two batches racing against the same item/location can't both pass
+    /// validation and jointly drive it negative. The result is reported back
+    /// over `responder`.
+    ApplyBatch(Vec<BatchLine>, oneshot::Sender<Result<(), String>>),
+    SetDiscontinued {
+        item_id: u32,
+        discontinued: bool,
+    },
+    /// Replaces the recipe attached to `item_id` wholesale (or removes it, if
+    /// `components` is empty).
+    SetRecipe {
+        item_id: u32,
+        output_quantity: i32,
+        components: Vec<(u32, i32)>,
+    },
+}
+
+/// Handle held by `MyApp` to talk to the persistence worker.
+pub struct PersistenceHandle {
+    pub commands: mpsc::Sender<Command>,
+    pub snapshot: watch::Receiver<Snapshot>,
+}
+
+/// Opens (creating if necessary) the SQLite database at `db_path`, loads any
+/// existing state, and spawns the background worker that owns the connection
+/// for the rest of the program's life.
+pub fn spawn(db_path: &Path, runtime: &tokio::runtime::Runtime) -> PersistenceHandle {
+    let conn = Connection::open(db_path).expect("failed to open inventory database");
+    migrate(&conn);
+    let initial = load_snapshot(&conn);
+
+    let (snapshot_tx, snapshot_rx) = watch::channel(initial);
+    let (command_tx, command_rx) = mpsc::channel(32);
+
+    runtime.spawn(worker_loop(conn, command_rx, snapshot_tx));
+
+    PersistenceHandle {
+        commands: command_tx,
+        snapshot: snapshot_rx,
+    }
+}
+
+fn migrate(conn: &Connection) {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS items (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            sku TEXT NOT NULL,
+            unit TEXT NOT NULL,
+            reorder_point INTEGER NOT NULL DEFAULT 0,
+            discontinued INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE IF NOT EXISTS item_stock (
+            item_id INTEGER NOT NULL REFERENCES items(id),
+            location TEXT NOT NULL,
+            quantity_on_hand INTEGER NOT NULL,
+            PRIMARY KEY (item_id, location)
+        );
+        CREATE TABLE IF NOT EXISTS transactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            year INTEGER NOT NULL,
+            month INTEGER NOT NULL,
+            day INTEGER NOT NULL,
+            item_id INTEGER NOT NULL,
+            location TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            note TEXT NOT NULL,
+            txn_type TEXT NOT NULL,
+            batch_id INTEGER,
+            posted_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS recipes (
+            item_id INTEGER PRIMARY KEY REFERENCES items(id),
+            output_quantity INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS recipe_components (
+            item_id INTEGER NOT NULL REFERENCES recipes(item_id),
+            component_item_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            PRIMARY KEY (item_id, component_item_id)
+        );",
+    )
+    .expect("failed to run inventory schema migration");
+}
+
+fn load_snapshot(conn: &Connection) -> Snapshot {
+    let mut stock_stmt = conn
+        .prepare(
+            "SELECT item_id, location, quantity_on_hand FROM item_stock ORDER BY item_id, location",
+        )
+        .expect("failed to prepare item_stock query");
+    let mut stock_by_item: HashMap<u32, Vec<LocationStock>> = HashMap::new();
+    let stock_rows = stock_stmt
+        .query_map([], |row| {
+            let item_id: u32 = row.get(0)?;
+            Ok((
+                item_id,
+                LocationStock {
+                    location: row.get(1)?,
+                    quantity_on_hand: row.get(2)?,
+                },
+            ))
+        })
+        .expect("failed to query item_stock");
+    for row in stock_rows.filter_map(Result::ok) {
+        let (item_id, stock) = row;
+        stock_by_item.entry(item_id).or_default().push(stock);
+    }
+
+    let mut component_stmt = conn
+        .prepare(
+            "SELECT item_id, component_item_id, quantity FROM recipe_components ORDER BY item_id, component_item_id",
+        )
+        .expect("failed to prepare recipe_components query");
+    let mut components_by_item: HashMap<u32, Vec<RecipeComponent>> = HashMap::new();
+    let component_rows = component_stmt
+        .query_map([], |row| {
+            let item_id: u32 = row.get(0)?;
+            Ok((
+                item_id,
+                RecipeComponent {
+                    item_id: row.get(1)?,
+                    quantity: row.get(2)?,
+                },
+            ))
+        })
+        .expect("failed to query recipe_components");
+    for row in component_rows.filter_map(Result::ok) {
+        let (item_id, component) = row;
+        components_by_item
+            .entry(item_id)
+            .or_default()
+            .push(component);
+    }
+
+    let mut recipe_stmt = conn
+        .prepare("SELECT item_id, output_quantity FROM recipes")
+        .expect("failed to prepare recipes query");
+    let mut recipe_by_item: HashMap<u32, Recipe> = HashMap::new();
+    let recipe_rows = recipe_stmt
+        .query_map([], |row| {
+            let item_id: u32 = row.get(0)?;
+            Ok((
+                item_id,
+                Recipe {
+                    output_quantity: row.get(1)?,
+                    components: components_by_item.remove(&item_id).unwrap_or_default(),
+                },
+            ))
+        })
+        .expect("failed to query recipes");
+    for row in recipe_rows.filter_map(Result::ok) {
+        let (item_id, recipe) = row;
+        recipe_by_item.insert(item_id, recipe);
+    }
+
+    let mut items_stmt = conn
+        .prepare("SELECT id, name, sku, unit, reorder_point, discontinued FROM items ORDER BY id")
+        .expect("failed to prepare items query");
+    let items: Vec<InventoryItem> = items_stmt
+        .query_map([], |row| {
+            let id: u32 = row.get(0)?;
+            Ok(InventoryItem {
+                id,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                unit: row.get(3)?,
+                stock: stock_by_item.remove(&id).unwrap_or_default(),
+                reorder_point: row.get(4)?,
+                discontinued: row.get::<_, i64>(5)? != 0,
+                recipe: None,
+            })
+        })
+        .expect("failed to query items")
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|mut item| {
+            item.recipe = recipe_by_item.remove(&item.id);
+            item
+        })
+        .collect();
+
+    let mut txns_stmt = conn
+        .prepare(
+            "SELECT year, month, day, item_id, location, quantity, note, txn_type, batch_id, posted_at FROM transactions ORDER BY id",
+        )
+        .expect("failed to prepare transactions query");
+    let transactions: Vec<Transaction> = txns_stmt
+        .query_map([], |row| {
+            let txn_type: String = row.get(7)?;
+            let posted_at: String = row.get(9)?;
+            Ok(Transaction {
+                date: (row.get(0)?, row.get(1)?, row.get(2)?),
+                item_id: row.get(3)?,
+                location: row.get(4)?,
+                quantity: row.get(5)?,
+                note: row.get(6)?,
+                txn_type: match txn_type.as_str() {
+                    "shipping" => TransactionType::Shipping,
+                    "transfer" => TransactionType::Transfer,
+                    "assembly" => TransactionType::Assembly,
+                    _ => TransactionType::Warehousing,
+                },
+                batch_id: row.get(8)?,
+                posted_at: chrono::NaiveDateTime::parse_from_str(&posted_at, POSTED_AT_FORMAT)
+                    .unwrap_or_else(|_| chrono::Local::now().naive_local()),
+            })
+        })
+        .expect("failed to query transactions")
+        .filter_map(Result::ok)
+        .collect();
+
+    Snapshot { items, transactions }
+}
+
+async fn worker_loop(
+    conn: Connection,
+    mut commands: mpsc::Receiver<Command>,
+    snapshot_tx: watch::Sender<Snapshot>,
+) {
+    while let Some(command) = commands.recv().await {
+        apply(&conn, command);
+        let fresh = load_snapshot(&conn);
+        if snapshot_tx.send(fresh).is_err() {
+            // No receivers left (app closed) - nothing more to do.
+            break;
+        }
+    }
+}
+
+fn apply(conn: &Connection, command: Command) {
+    match command {
+        Command::AddItem {
+            name,
+            sku,
+            unit,
+            location,
+            quantity_on_hand,
+            reorder_point,
+        } => {
+            conn.execute(
+                "INSERT INTO items (name, sku, unit, reorder_point) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![name, sku, unit, reorder_point],
+            )
+            .expect("failed to insert item");
+            let item_id = conn.last_insert_rowid();
+            conn.execute(
+                "INSERT INTO item_stock (item_id, location, quantity_on_hand) VALUES (?1, ?2, ?3)",
+                rusqlite::params![item_id, location, quantity_on_hand],
+            )
+            .expect("failed to seed initial item stock");
+        }
+        Command::ApplyBatch(lines, responder) => {
+            let result = apply_batch(conn, lines);
+            let _ = responder.send(result);
+        }
+        Command::SetDiscontinued {
+            item_id,
+            discontinued,
+        } => {
+            conn.execute(
+                "UPDATE items SET discontinued = ?1 WHERE id = ?2",
+                rusqlite::params![discontinued, item_id],
+            )
+            .expect("failed to update discontinued flag");
+        }
+        Command::SetRecipe {
+            item_id,
+            output_quantity,
+            components,
+        } => {
+            let tx = conn
+                .unchecked_transaction()
+                .expect("failed to start recipe transaction");
+            tx.execute(
+                "DELETE FROM recipe_components WHERE item_id = ?1",
+                rusqlite::params![item_id],
+            )
+            .expect("failed to clear recipe components");
+            if components.is_empty() {
+                tx.execute(
+                    "DELETE FROM recipes WHERE item_id = ?1",
+                    rusqlite::params![item_id],
+                )
+                .expect("failed to remove recipe");
+            } else {
+                tx.execute(
+                    "INSERT INTO recipes (item_id, output_quantity) VALUES (?1, ?2)
+                     ON CONFLICT(item_id) DO UPDATE SET output_quantity = excluded.output_quantity",
+                    rusqlite::params![item_id, output_quantity],
+                )
+                .expect("failed to upsert recipe");
+            }
+            for (component_item_id, quantity) in components {
+                tx.execute(
+                    "INSERT INTO recipe_components (item_id, component_item_id, quantity) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![item_id, component_item_id, quantity],
+                )
+                .expect("failed to insert recipe component");
+            }
+            tx.commit().expect("failed to commit recipe transaction");
+        }
+    }
+}
+
+/// Format `posted_at` is stored/parsed in - plain local wall-clock time,
+/// second resolution is enough to tell "2 hours ago" from "3 hours ago".
+const POSTED_AT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn txn_type_str(txn_type: &TransactionType) -> &'static str {
+    match txn_type {
+        TransactionType::Warehousing => "warehousing",
+        TransactionType::Shipping => "shipping",
+        TransactionType::Transfer => "transfer",
+        TransactionType::Assembly => "assembly",
+    }
+}
+
+/// Applies every line of a staged transaction in one SQLite transaction, so a
+/// batch either lands completely or not at all. The persisted rows share a
+/// `batch_id` (the id of the batch's first row) so they can be identified
+/// and reversed as a unit later.
+///
+/// Re-validates every line against the database's current stock (not the
+/// caller's snapshot) before touching any row, aggregating lines against the
+/// same (item, location) pair the same way `TransactionBuilder::validate`
+/// does client-side. This is the authoritative check: the client-side one is
+/// just an early, friendlier rejection for the common case. If any line
+/// would drive a location below zero, nothing in the batch is applied - the
+/// whole SQLite transaction is rolled back (by returning before it commits -
+/// rusqlite rolls an uncommitted transaction back when it's dropped).
+fn apply_batch(conn: &Connection, lines: Vec<BatchLine>) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .expect("failed to start batch transaction");
+    let mut projected: HashMap<(u32, String), i32> = HashMap::new();
+    for line in &lines {
+        let key = (line.item_id, line.location.clone());
+        let current = match projected.get(&key) {
+            Some(&v) => v,
+            None => tx
+                .query_row(
+                    "SELECT quantity_on_hand FROM item_stock WHERE item_id = ?1 AND location = ?2",
+                    rusqlite::params![line.item_id, line.location],
+                    |row| row.get(0),
+                )
+                .optional()
+                .expect("failed to query current stock")
+                .unwrap_or(0),
+        };
+        let after = current + line.delta;
+        if after < 0 {
+            return Err(format!(
+                "item {} at {} would go below zero",
+                line.item_id, line.location
+            ));
+        }
+        projected.insert(key, after);
+    }
+
+    let posted_at = chrono::Local::now()
+        .naive_local()
+        .format(POSTED_AT_FORMAT)
+        .to_string();
+    let mut batch_id: Option<i64> = None;
+    for line in &lines {
+        tx.execute(
+            "INSERT INTO item_stock (item_id, location, quantity_on_hand) VALUES (?1, ?2, ?3)
+             ON CONFLICT(item_id, location) DO UPDATE SET quantity_on_hand = quantity_on_hand + excluded.quantity_on_hand",
+            rusqlite::params![line.item_id, line.location, line.delta],
+        )
+        .expect("failed to update item stock in batch");
+        tx.execute(
+            "INSERT INTO transactions (year, month, day, item_id, location, quantity, note, txn_type, batch_id, posted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                line.date.0,
+                line.date.1,
+                line.date.2,
+                line.item_id,
+                line.location,
+                line.delta,
+                line.note,
+                txn_type_str(&line.txn_type),
+                batch_id,
+                posted_at,
+            ],
+        )
+        .expect("failed to insert batch transaction line");
+        if batch_id.is_none() {
+            let id = tx.last_insert_rowid();
+            tx.execute(
+                "UPDATE transactions SET batch_id = ?1 WHERE id = ?1",
+                rusqlite::params![id],
+            )
+            .expect("failed to stamp batch id");
+            batch_id = Some(id);
+        }
+    }
+    tx.commit().expect("failed to commit batch transaction");
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Staged (multi-line) transactions.
+//!
+//! A `TransactionBuilder` accumulates several proposed line items before
+//! anything is committed. The whole batch is validated against current
+//! stock at once - so two lines touching the same item can't individually
+//! look fine but collectively oversell it - then committed or discarded
+//! together. Every committed batch is recorded as an `ItemStateProxy` list
+//! so it can be reversed (or re-applied) as a unit via the `UndoStack`.
+
+use std::collections::HashMap;
+
+use crate::model::{InventoryItem, TransactionType};
+
+/// One proposed adjustment within a not-yet-committed batch.
+#[derive(Clone)]
+pub struct ProposedLine {
+    pub item_index: usize,
+    pub location: String,
+    pub delta: i32,
+    pub note: String,
+}
+
+#[derive(Default)]
+pub struct TransactionBuilder {
+    lines: Vec<ProposedLine>,
+}
+
+impl TransactionBuilder {
+    pub fn add_line(&mut self, item_index: usize, location: String, delta: i32, note: String) {
+        self.lines.push(ProposedLine {
+            item_index,
+            location,
+            delta,
+            note,
+        });
+    }
+
+    pub fn remove_line(&mut self, index: usize) {
+        if index < self.lines.len() {
+            self.lines.remove(index);
+        }
+    }
+
+    pub fn lines(&self) -> &[ProposedLine] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Validates the whole batch against `items`' current per-location stock,
+    /// aggregating multiple lines against the same (item, location) pair so a
+    /// batch can't oversell a location through several individually
+    /// plausible-looking lines.
+    pub fn validate(&self, items: &[InventoryItem]) -> Result<(), String> {
+        let mut projected: HashMap<(usize, String), i32> = HashMap::new();
+        for line in &self.lines {
+            let item = items
+                .get(line.item_index)
+                .ok_or_else(|| "unknown item in batch".to_string())?;
+            let key = (line.item_index, line.location.clone());
+            let running = projected
+                .entry(key)
+                .or_insert_with(|| item.quantity_at(&line.location));
+            *running += line.delta;
+            if *running < 0 {
+                return Err(format!(
+                    "{} at {} would go below zero",
+                    item.name, line.location
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The before/after quantity of one (item, location) pair touched by a
+/// committed batch.
+#[derive(Clone)]
+pub struct ItemStateProxy {
+    pub item_id: u32,
+    pub location: String,
+    pub before: i32,
+    pub after: i32,
+}
+
+/// A committed batch, recorded so it can be undone or redone as a unit. Keeps
+/// the original `txn_type` so a reversal can be logged under it instead of
+/// guessed from the sign of the delta (a reversed Transfer or Assembly is
+/// still a Transfer or Assembly in the audit trail, not a plain
+/// Warehousing/Shipping line).
+#[derive(Clone)]
+pub struct CommittedBatch {
+    pub txn_type: TransactionType,
+    pub entries: Vec<ItemStateProxy>,
+}
+
+/// A reversal/redo result: the `txn_type` to log it under, alongside the
+/// `(item_id, location, delta)` triples to apply.
+type BatchReplay = (TransactionType, Vec<(u32, String, i32)>);
+
+/// Undo/redo history of committed batches. Undoing or redoing re-applies the
+/// reversing (or original) deltas as a brand new batch rather than deleting
+/// history, so the transaction log stays a complete audit trail.
+///
+/// Shared across every tab that posts batches (Warehousing, Shipping,
+/// Transfer, Assemble): undo/redo always acts on the single most recent
+/// batch regardless of which tab posted it, so the UI surfaces the
+/// Undo/Redo controls on every one of those tabs rather than just one.
+#[derive(Default)]
+pub struct UndoStack {
+    done: Vec<CommittedBatch>,
+    undone: Vec<CommittedBatch>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, batch: CommittedBatch) {
+        self.done.push(batch);
+        self.undone.clear();
+    }
+
+    /// Pops the most recently committed batch and returns its `txn_type`
+    /// alongside the `(item_id, location, delta)` triples needed to reverse
+    /// it.
+    pub fn undo(&mut self) -> Option<BatchReplay> {
+        let batch = self.done.pop()?;
+        let reversal = batch
+            .entries
+            .iter()
+            .map(|e| (e.item_id, e.location.clone(), e.before - e.after))
+            .collect();
+        let txn_type = batch.txn_type.clone();
+        self.undone.push(batch);
+        Some((txn_type, reversal))
+    }
+
+    /// Pops the most recently undone batch and returns its `txn_type`
+    /// alongside the `(item_id, location, delta)` triples needed to redo it.
+    pub fn redo(&mut self) -> Option<BatchReplay> {
+        let batch = self.undone.pop()?;
+        let redo = batch
+            .entries
+            .iter()
+            .map(|e| (e.item_id, e.location.clone(), e.after - e.before))
+            .collect();
+        let txn_type = batch.txn_type.clone();
+        self.done.push(batch);
+        Some((txn_type, redo))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}